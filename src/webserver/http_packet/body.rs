@@ -0,0 +1,196 @@
+//! Message body abstraction.
+//!
+//! Replaces a bare `Option<Vec<u8>>` so a message can carry an in-memory
+//! buffer, a bounded reader that is streamed on demand, or nothing at all —
+//! letting the server move large uploads, downloads and proxied responses
+//! without holding the whole payload in RAM.
+use std::io::{self, Read};
+
+/// How a message body is framed on the wire, so higher layers can decide
+/// between a `Content-Length`, chunked transfer-coding, or no body at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BodySize {
+    /// No body.
+    Empty,
+    /// A body of known length, sent with a `Content-Length`.
+    Sized(u64),
+    /// A body of unknown length, sent with `Transfer-Encoding: chunked`.
+    Chunked,
+}
+
+/// The body of an [`HTTPMessage`](super::HTTPMessage).
+///
+/// A [`Reader`](Body::Reader) body is single-use and not clonable, which is
+/// why [`HTTPMessage`](super::HTTPMessage) is not `Clone`.
+#[derive(Default)]
+pub(crate) enum Body {
+    /// No body.
+    #[default]
+    Empty,
+    /// A fully buffered, in-memory body.
+    Bytes(Vec<u8>),
+    /// A streamed body backed by a reader, with an optional known length.
+    Reader {
+        reader: Box<dyn Read + Send>,
+        /// Length in bytes if known ahead of time, else `None`.
+        len: Option<u64>,
+    },
+}
+
+/// Errors returned when collecting a [`Body`] into memory.
+#[derive(Debug)]
+pub(crate) enum BodyError {
+    /// The body exceeded the caller-supplied maximum size.
+    TooLarge { max: usize },
+    /// The underlying reader failed.
+    Io(io::Error),
+}
+
+impl Body {
+    /// Returns an empty body.
+    pub(crate) fn empty() -> Self {
+        Body::Empty
+    }
+
+    /// Returns `true` if this body is known to be empty.
+    pub(crate) fn is_empty(&self) -> bool {
+        matches!(self, Body::Empty) || matches!(self, Body::Bytes(b) if b.is_empty())
+    }
+
+    /// Returns the body length in bytes when it is known ahead of time.
+    ///
+    /// A streamed body of unknown length returns `None`, signalling that the
+    /// sender must use chunked framing rather than a `Content-Length`.
+    pub(crate) fn len(&self) -> Option<u64> {
+        match self {
+            Body::Empty => Some(0),
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Reader { len, .. } => *len,
+        }
+    }
+
+    /// Returns `true` if the body length is known ahead of time.
+    pub(crate) fn is_known_length(&self) -> bool {
+        self.len().is_some()
+    }
+
+    /// Wraps a reader as a streamed body with an optional known length.
+    pub(crate) fn from_reader(reader: Box<dyn Read + Send>, len: Option<u64>) -> Self {
+        Body::Reader { reader, len }
+    }
+
+    /// Collects the body into a contiguous byte buffer of at most `max_size`
+    /// bytes.
+    ///
+    /// A body larger than `max_size` yields [`BodyError::TooLarge`] instead of
+    /// being read into memory, guarding against memory exhaustion on untrusted
+    /// input.
+    pub(crate) fn collect(self, max_size: usize) -> Result<Vec<u8>, BodyError> {
+        match self {
+            Body::Empty => Ok(Vec::new()),
+            Body::Bytes(bytes) => {
+                if bytes.len() > max_size {
+                    return Err(BodyError::TooLarge { max: max_size });
+                }
+                Ok(bytes)
+            }
+            Body::Reader { reader, .. } => {
+                // Read one byte past the limit so an over-long body is caught
+                // without buffering the whole thing.
+                let mut buf = Vec::new();
+                let read = reader
+                    .take(max_size as u64 + 1)
+                    .read_to_end(&mut buf)
+                    .map_err(BodyError::Io)?;
+                if read > max_size {
+                    return Err(BodyError::TooLarge { max: max_size });
+                }
+                Ok(buf)
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Body::Empty => f.write_str("Body::Empty"),
+            Body::Bytes(bytes) => f.debug_tuple("Body::Bytes").field(&bytes.len()).finish(),
+            Body::Reader { len, .. } => f.debug_struct("Body::Reader").field("len", len).finish(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            Body::Empty
+        } else {
+            Body::Bytes(bytes)
+        }
+    }
+}
+
+impl From<Option<Vec<u8>>> for Body {
+    fn from(bytes: Option<Vec<u8>>) -> Self {
+        match bytes {
+            Some(bytes) => Body::from(bytes),
+            None => Body::Empty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_and_bytes_report_known_length() {
+        assert_eq!(Body::Empty.len(), Some(0));
+        assert!(Body::Empty.is_known_length());
+        assert_eq!(Body::from(b"hello".to_vec()).len(), Some(5));
+    }
+
+    #[test]
+    fn reader_without_length_is_unknown() {
+        let body = Body::from_reader(Box::new(io::empty()), None);
+        assert_eq!(body.len(), None);
+        assert!(!body.is_known_length());
+    }
+
+    #[test]
+    fn vec_adapter_maps_empty_to_empty_variant() {
+        assert!(matches!(Body::from(Vec::new()), Body::Empty));
+        assert!(matches!(Body::from(vec![1, 2, 3]), Body::Bytes(_)));
+    }
+
+    #[test]
+    fn collect_returns_buffered_bytes() {
+        let body = Body::from(b"payload".to_vec());
+        assert_eq!(body.collect(64).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn collect_rejects_oversize_buffer() {
+        let body = Body::from(vec![0u8; 100]);
+        assert!(matches!(
+            body.collect(10),
+            Err(BodyError::TooLarge { max: 10 })
+        ));
+    }
+
+    #[test]
+    fn collect_rejects_oversize_reader_without_full_buffering() {
+        let body = Body::from_reader(Box::new(io::repeat(b'x').take(1000)), None);
+        assert!(matches!(
+            body.collect(16),
+            Err(BodyError::TooLarge { max: 16 })
+        ));
+    }
+
+    #[test]
+    fn collect_reads_reader_within_limit() {
+        let body = Body::from_reader(Box::new(&b"abc"[..]), Some(3));
+        assert_eq!(body.collect(16).unwrap(), b"abc");
+    }
+}
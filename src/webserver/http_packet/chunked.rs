@@ -0,0 +1,193 @@
+//! `Transfer-Encoding: chunked` codec.
+//!
+//! Chunked framing is the only way to send a body of unknown length over
+//! HTTP/1.1. The [`decode`] function reassembles a chunked stream into a plain
+//! body (plus any trailer headers); [`encode`] performs the reverse.
+use super::header::HTTPHeader;
+
+/// Errors produced while decoding a chunked body.
+#[derive(Debug)]
+pub(crate) enum ChunkedError {
+    /// More input is needed to finish the current chunk or framing.
+    Incomplete,
+    /// The stream violated the chunked grammar (bad size line, missing CRLF…).
+    Malformed,
+    /// The assembled body exceeded the configured maximum size.
+    TooLarge { max: usize },
+}
+
+/// A successfully decoded chunked body.
+#[derive(Debug)]
+pub(crate) struct Decoded {
+    /// The reassembled body bytes.
+    pub body: Vec<u8>,
+    /// Trailer headers that followed the terminating zero-size chunk.
+    pub trailers: HTTPHeader,
+    /// Number of input bytes consumed, so a read loop can advance its buffer.
+    pub consumed: usize,
+}
+
+/// Decodes a chunked body from `input`, rejecting a total body larger than
+/// `max_size`.
+///
+/// Each chunk is a hex size line terminated by CRLF (any `;`-prefixed chunk
+/// extension is ignored), followed by exactly that many body bytes and a
+/// trailing CRLF. A zero-size chunk ends the body; optional trailer headers
+/// then run until a blank line.
+pub(crate) fn decode(input: &[u8], max_size: usize) -> Result<Decoded, ChunkedError> {
+    let mut pos = 0;
+    let mut body = Vec::new();
+
+    loop {
+        let (line, next) = read_line(input, pos).ok_or(ChunkedError::Incomplete)?;
+        // Drop any chunk extension after the first ';'.
+        let size_bytes = line.split(|&b| b == b';').next().unwrap_or(line);
+        let size = parse_hex(size_bytes).ok_or(ChunkedError::Malformed)?;
+        pos = next;
+
+        if size == 0 {
+            let trailers = read_trailers(input, &mut pos)?;
+            return Ok(Decoded {
+                body,
+                trailers,
+                consumed: pos,
+            });
+        }
+
+        let size = size as usize;
+        if body.len().saturating_add(size) > max_size {
+            return Err(ChunkedError::TooLarge { max: max_size });
+        }
+        let end = pos.checked_add(size).ok_or(ChunkedError::Malformed)?;
+        if input.len() < end + 2 {
+            return Err(ChunkedError::Incomplete);
+        }
+        body.extend_from_slice(&input[pos..end]);
+        if &input[end..end + 2] != b"\r\n" {
+            return Err(ChunkedError::Malformed);
+        }
+        pos = end + 2;
+    }
+}
+
+/// Encodes `body` as a chunked stream, splitting it into chunks of at most
+/// `chunk_size` bytes and appending the terminating `0\r\n\r\n`.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+pub(crate) fn encode(body: &[u8], chunk_size: usize) -> Vec<u8> {
+    assert!(chunk_size > 0, "chunk_size must be non-zero");
+    let mut out = Vec::with_capacity(body.len() + body.len() / chunk_size + 8);
+    for chunk in body.chunks(chunk_size) {
+        out.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+        out.extend_from_slice(chunk);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"0\r\n\r\n");
+    out
+}
+
+/// Returns the bytes up to (but excluding) the next CRLF and the index just
+/// past it, or `None` if no CRLF is present yet.
+fn read_line(input: &[u8], from: usize) -> Option<(&[u8], usize)> {
+    let rest = input.get(from..)?;
+    let nl = rest.windows(2).position(|w| w == b"\r\n")?;
+    Some((&rest[..nl], from + nl + 2))
+}
+
+/// Parses an ASCII hex number, rejecting empty or non-hex input.
+fn parse_hex(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &b in bytes {
+        let digit = (b as char).to_digit(16)?;
+        value = value.checked_mul(16)?.checked_add(digit as u64)?;
+    }
+    Some(value)
+}
+
+/// Reads trailer headers until a blank line, advancing `pos`.
+fn read_trailers(input: &[u8], pos: &mut usize) -> Result<HTTPHeader, ChunkedError> {
+    let mut trailers = HTTPHeader::new();
+    loop {
+        let (line, next) = read_line(input, *pos).ok_or(ChunkedError::Incomplete)?;
+        *pos = next;
+        if line.is_empty() {
+            return Ok(trailers);
+        }
+        let colon = line.iter().position(|&b| b == b':').ok_or(ChunkedError::Malformed)?;
+        let name = String::from_utf8_lossy(&line[..colon]);
+        let value = String::from_utf8_lossy(&line[colon + 1..]);
+        trailers.set(name.trim(), value.trim());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_multiple_chunks() {
+        let decoded = decode(b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n", 1024).unwrap();
+        assert_eq!(decoded.body, b"Wikipedia");
+        assert_eq!(decoded.consumed, 24);
+        assert!(decoded.trailers.iter().next().is_none());
+    }
+
+    #[test]
+    fn ignores_chunk_extensions() {
+        let decoded = decode(b"3;name=value\r\nabc\r\n0\r\n\r\n", 1024).unwrap();
+        assert_eq!(decoded.body, b"abc");
+    }
+
+    #[test]
+    fn reads_trailers_after_zero_chunk() {
+        let decoded = decode(b"0\r\nX-Checksum: 42\r\n\r\n", 1024).unwrap();
+        assert!(decoded.body.is_empty());
+        assert_eq!(decoded.trailers.get("x-checksum"), Some("42"));
+    }
+
+    #[test]
+    fn incomplete_when_size_line_unterminated() {
+        assert!(matches!(decode(b"4\r\nWi", 1024), Err(ChunkedError::Incomplete)));
+        assert!(matches!(decode(b"4", 1024), Err(ChunkedError::Incomplete)));
+    }
+
+    #[test]
+    fn malformed_size_line_rejected() {
+        assert!(matches!(decode(b"zz\r\n", 1024), Err(ChunkedError::Malformed)));
+    }
+
+    #[test]
+    fn missing_chunk_crlf_rejected() {
+        assert!(matches!(
+            decode(b"4\r\nWikiXX0\r\n\r\n", 1024),
+            Err(ChunkedError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn oversize_body_rejected() {
+        assert!(matches!(
+            decode(b"8\r\noverflow!\r\n0\r\n\r\n", 4),
+            Err(ChunkedError::TooLarge { max: 4 })
+        ));
+    }
+
+    #[test]
+    fn encode_then_decode_round_trips() {
+        let body = b"the quick brown fox";
+        let encoded = encode(body, 4);
+        let decoded = decode(&encoded, 1024).unwrap();
+        assert_eq!(decoded.body, body);
+    }
+
+    #[test]
+    #[should_panic]
+    fn encode_rejects_zero_chunk_size() {
+        encode(b"x", 0);
+    }
+}
@@ -0,0 +1,444 @@
+//! Case-insensitive HTTP header collection.
+//!
+//! Header field names are compared case-insensitively per RFC 7230, so the
+//! map is keyed by an ASCII-lowercased copy of the name while the value is
+//! stored verbatim.
+use std::collections::HashMap;
+use std::fmt;
+
+use super::body::BodySize;
+
+/// A normalized map of HTTP header fields.
+///
+/// Lookups and stores are case-insensitive in the field name; values are
+/// preserved exactly as supplied.
+///
+/// When raw capture is enabled (see [`with_raw`](Self::with_raw)), a parallel
+/// ordered list records the original `(name, value)` byte pairs exactly as
+/// received — preserving casing, duplicate ordering and unusual whitespace —
+/// for fingerprinting, signature verification and byte-faithful proxying. The
+/// list is never consulted for lookups, which always go through the map.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HTTPHeader {
+    map: HashMap<String, String>,
+    /// Original on-the-wire pairs in arrival order, or `None` when capture is
+    /// disabled (the common case, avoiding the extra allocation).
+    raw: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+}
+
+impl HTTPHeader {
+    /// Creates an empty header collection with raw capture disabled.
+    pub(crate) fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+            raw: None,
+        }
+    }
+
+    /// Creates an empty header collection that also records the raw,
+    /// on-the-wire `(name, value)` byte pairs as they are appended.
+    pub(crate) fn with_raw() -> Self {
+        Self {
+            map: HashMap::new(),
+            raw: Some(Vec::new()),
+        }
+    }
+
+    /// Returns `true` if raw on-the-wire pairs are being captured.
+    pub(crate) fn captures_raw(&self) -> bool {
+        self.raw.is_some()
+    }
+
+    /// Appends a header exactly as received, updating both the normalized map
+    /// and — when enabled — the raw ordered list.
+    ///
+    /// Unlike [`set`](Self::set) this never replaces an existing field: the raw
+    /// list keeps every duplicate verbatim (casing and whitespace included) in
+    /// arrival order, and the normalized map combines duplicates the same way
+    /// [`append`](Self::append) does. `name`/`value` are the verbatim on-the-wire
+    /// bytes (the value still carries its leading space after the colon); the
+    /// map stores a trimmed, lowercased view.
+    pub(crate) fn push_raw(&mut self, name: &[u8], value: &[u8]) {
+        if let Some(raw) = self.raw.as_mut() {
+            raw.push((name.to_vec(), value.to_vec()));
+        }
+        let name = String::from_utf8_lossy(name);
+        let value = String::from_utf8_lossy(value);
+        self.append(name.trim(), value.trim());
+    }
+
+    /// Iterates the captured raw `(name, value)` byte pairs in arrival order.
+    ///
+    /// Yields nothing when raw capture is disabled.
+    pub(crate) fn raw_pairs(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.raw
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.as_slice(), value.as_slice()))
+    }
+
+    /// Returns the value of `name`, if present, ignoring case.
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.map.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /// Inserts or replaces the value for `name`.
+    pub(crate) fn set(&mut self, name: &str, value: &str) {
+        self.map.insert(name.to_ascii_lowercase(), value.to_string());
+    }
+
+    /// Adds a header field without discarding an existing one.
+    ///
+    /// Repeated field lines are merged following RFC 7230 §3.2.2: a second
+    /// occurrence is appended to the first with a comma so list headers like
+    /// `Connection` and `Accept-Encoding` keep every token. `Set-Cookie` is the
+    /// one field that must not be comma-combined, so its values are kept on
+    /// separate logical lines (joined with `\n`) and re-emitted as distinct
+    /// headers by [`to_bytes`](super::HTTPMessage::to_bytes).
+    pub(crate) fn append(&mut self, name: &str, value: &str) {
+        let key = name.to_ascii_lowercase();
+        match self.map.get_mut(&key) {
+            None => {
+                self.map.insert(key, value.to_string());
+            }
+            Some(existing) => {
+                let separator = if key == "set-cookie" { "\n" } else { ", " };
+                existing.push_str(separator);
+                existing.push_str(value);
+            }
+        }
+    }
+
+    /// Removes `name`, returning its previous value if any.
+    pub(crate) fn remove(&mut self, name: &str) -> Option<String> {
+        self.map.remove(&name.to_ascii_lowercase())
+    }
+
+    /// Returns `true` if `name` is present.
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.map.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Iterates the normalized `(name, value)` fields.
+    ///
+    /// Names are the lowercased lookup keys; use [`raw_pairs`](Self::raw_pairs)
+    /// when the exact on-the-wire casing matters.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.map.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the `Content-Length` as a number, if present and valid.
+    pub(crate) fn content_length(&self) -> Option<u64> {
+        self.get("content-length")
+            .and_then(|v| v.trim().parse().ok())
+    }
+
+    /// Returns `true` if `Transfer-Encoding` requests chunked framing.
+    pub(crate) fn is_chunked(&self) -> bool {
+        self.get("transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+    }
+
+    /// Resolves the body framing these headers describe, with chunked taking
+    /// precedence over `Content-Length` per RFC 7230.
+    pub(crate) fn framing(&self) -> BodySize {
+        if self.is_chunked() {
+            BodySize::Chunked
+        } else {
+            match self.content_length() {
+                Some(0) | None => BodySize::Empty,
+                Some(len) => BodySize::Sized(len),
+            }
+        }
+    }
+
+    /// Splits a comma-delimited list header (such as `Connection` or
+    /// `Accept-Encoding`) into trimmed, non-empty tokens.
+    ///
+    /// Yields nothing when the header is absent.
+    pub(crate) fn list_values(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.get(name)
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+    }
+
+    /// Parses the `Cookie` header into its `name`/`value` pairs.
+    pub(crate) fn cookies(&self) -> Vec<(String, String)> {
+        self.get("cookie")
+            .into_iter()
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| {
+                let pair = pair.trim();
+                if pair.is_empty() {
+                    return None;
+                }
+                match pair.split_once('=') {
+                    Some((name, value)) => {
+                        Some((name.trim().to_string(), value.trim().to_string()))
+                    }
+                    None => Some((pair.to_string(), String::new())),
+                }
+            })
+            .collect()
+    }
+
+    /// Parses the `Set-Cookie` header into a typed [`SetCookie`].
+    pub(crate) fn set_cookie(&self) -> Option<SetCookie> {
+        self.get("set-cookie").and_then(SetCookie::parse)
+    }
+
+    /// Writes a [`SetCookie`] back out as the `Set-Cookie` header.
+    pub(crate) fn set_set_cookie(&mut self, cookie: &SetCookie) {
+        self.set("Set-Cookie", &cookie.to_string());
+    }
+}
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SameSite {
+    None,
+    Lax,
+    Strict,
+}
+
+impl SameSite {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(SameSite::None),
+            "lax" => Some(SameSite::Lax),
+            "strict" => Some(SameSite::Strict),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SameSite::None => "None",
+            SameSite::Lax => "Lax",
+            SameSite::Strict => "Strict",
+        }
+    }
+}
+
+/// A parsed `Set-Cookie` header.
+///
+/// Attributes are kept in their original order and casing so a get-then-set
+/// round-trips faithfully; the typed accessors ([`secure`](Self::secure),
+/// [`same_site`](Self::same_site)…) read that list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SetCookie {
+    pub name: String,
+    pub value: String,
+    /// Cookie attributes as `(key, optional value)`, e.g. `("Secure", None)`
+    /// or `("SameSite", Some("Lax"))`, in the order they appeared.
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl SetCookie {
+    /// Creates a cookie with the given name and value and no attributes.
+    pub(crate) fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Parses a raw `Set-Cookie` value, returning `None` if the required
+    /// `name=value` pair is missing.
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        let mut parts = raw.split(';');
+        let (name, value) = parts.next()?.trim().split_once('=')?;
+        let attributes = parts
+            .filter_map(|part| {
+                let part = part.trim();
+                if part.is_empty() {
+                    return None;
+                }
+                Some(match part.split_once('=') {
+                    Some((key, value)) => (key.trim().to_string(), Some(value.trim().to_string())),
+                    None => (part.to_string(), None),
+                })
+            })
+            .collect();
+        Some(Self {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            attributes,
+        })
+    }
+
+    /// Returns `true` if the `Secure` flag is set.
+    pub(crate) fn secure(&self) -> bool {
+        self.has_flag("secure")
+    }
+
+    /// Returns `true` if the `HttpOnly` flag is set.
+    pub(crate) fn http_only(&self) -> bool {
+        self.has_flag("httponly")
+    }
+
+    /// Returns the parsed `SameSite` attribute, if present and recognized.
+    pub(crate) fn same_site(&self) -> Option<SameSite> {
+        self.attribute("samesite").flatten().and_then(SameSite::parse)
+    }
+
+    /// Sets or clears the `Secure` flag.
+    pub(crate) fn set_secure(&mut self, secure: bool) {
+        self.set_flag("Secure", secure);
+    }
+
+    /// Sets or clears the `HttpOnly` flag.
+    pub(crate) fn set_http_only(&mut self, http_only: bool) {
+        self.set_flag("HttpOnly", http_only);
+    }
+
+    /// Sets the `SameSite` attribute.
+    pub(crate) fn set_same_site(&mut self, same_site: SameSite) {
+        self.set_attribute("SameSite", Some(same_site.as_str().to_string()));
+    }
+
+    fn has_flag(&self, key: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(key))
+    }
+
+    /// Returns `Some(value)` when the attribute exists, with the inner option
+    /// distinguishing a valued attribute from a bare flag.
+    fn attribute(&self, key: &str) -> Option<Option<&str>> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_deref())
+    }
+
+    fn set_flag(&mut self, key: &str, present: bool) {
+        if present {
+            self.set_attribute(key, None);
+        } else {
+            self.attributes.retain(|(name, _)| !name.eq_ignore_ascii_case(key));
+        }
+    }
+
+    fn set_attribute(&mut self, key: &str, value: Option<String>) {
+        if let Some(entry) = self
+            .attributes
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        {
+            entry.1 = value;
+        } else {
+            self.attributes.push((key.to_string(), value));
+        }
+    }
+}
+
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        for (key, value) in &self.attributes {
+            match value {
+                Some(value) => write!(f, "; {key}={value}")?,
+                None => write!(f, "; {key}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_ignore_field_name_case() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Content-Type", "text/plain");
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+        assert!(headers.contains("CONTENT-TYPE"));
+    }
+
+    #[test]
+    fn content_length_and_framing() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Content-Length", "42");
+        assert_eq!(headers.content_length(), Some(42));
+        assert_eq!(headers.framing(), BodySize::Sized(42));
+
+        headers.set("Transfer-Encoding", "chunked");
+        assert!(headers.is_chunked());
+        assert_eq!(headers.framing(), BodySize::Chunked);
+    }
+
+    #[test]
+    fn list_values_splits_and_trims() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Accept-Encoding", "gzip, br ,deflate");
+        let tokens: Vec<_> = headers.list_values("accept-encoding").collect();
+        assert_eq!(tokens, ["gzip", "br", "deflate"]);
+        assert_eq!(headers.list_values("connection").count(), 0);
+    }
+
+    #[test]
+    fn append_comma_combines_and_preserves_set_cookie() {
+        let mut headers = HTTPHeader::new();
+        headers.append("Connection", "keep-alive");
+        headers.append("Connection", "Upgrade");
+        assert_eq!(headers.get("connection"), Some("keep-alive, Upgrade"));
+
+        headers.append("Set-Cookie", "a=1");
+        headers.append("Set-Cookie", "b=2");
+        assert_eq!(headers.get("set-cookie"), Some("a=1\nb=2"));
+    }
+
+    #[test]
+    fn cookie_header_parses_pairs() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Cookie", "id=abc; theme=dark; flag");
+        assert_eq!(
+            headers.cookies(),
+            vec![
+                ("id".to_string(), "abc".to_string()),
+                ("theme".to_string(), "dark".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_cookie_parses_flags_and_samesite() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Set-Cookie", "sid=xyz; Secure; HttpOnly; SameSite=None");
+        let cookie = headers.set_cookie().unwrap();
+        assert_eq!(cookie.name, "sid");
+        assert_eq!(cookie.value, "xyz");
+        assert!(cookie.secure());
+        assert!(cookie.http_only());
+        assert_eq!(cookie.same_site(), Some(SameSite::None));
+    }
+
+    #[test]
+    fn set_cookie_round_trips_without_corruption() {
+        let raw = "sid=xyz; Path=/; Secure; SameSite=Lax";
+        let cookie = SetCookie::parse(raw).unwrap();
+        assert_eq!(cookie.to_string(), raw);
+    }
+
+    #[test]
+    fn set_cookie_setters_mutate_in_place() {
+        let mut cookie = SetCookie::new("sid", "xyz");
+        cookie.set_secure(true);
+        cookie.set_same_site(SameSite::Strict);
+        assert!(cookie.secure());
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+
+        cookie.set_secure(false);
+        assert!(!cookie.secure());
+        // Re-setting SameSite updates the existing attribute rather than duplicating it.
+        cookie.set_same_site(SameSite::Lax);
+        assert_eq!(cookie.to_string(), "sid=xyz; SameSite=Lax");
+    }
+}
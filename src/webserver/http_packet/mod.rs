@@ -3,33 +3,517 @@
 //! This module is **internal**; users interact with the higher-level
 //! [`HTTPRequest`](crate::webserver::requests::HTTPRequest) and
 //! [`HTTPResponse`](crate::webserver::responses::HTTPResponse) types instead.
+pub mod body;
+pub mod chunked;
 pub mod header;
+use body::{Body, BodySize};
 use header::HTTPHeader;
 
 /// An HTTP/1.1 message (request or response) without any semantic
 /// interpretation.
 ///
-/// Cloning is cheap: headers are reference-counted and the body is an
-/// optional `Vec<u8>`.
-#[derive(Clone, Debug)]
+/// Not `Clone`: the [`Body`] may be a single-use stream.
+#[derive(Debug)]
 pub(crate) struct HTTPMessage {
+    /// The raw start-line as received, e.g. `"GET / HTTP/1.1"` or
+    /// `"HTTP/1.1 200 OK"`. Empty for messages built in memory; the higher
+    /// request/response types parse the method/target or status code out of it.
+    pub start_line: String,
     /// Protocol version as received on the wire, e.g. `"HTTP/1.1"`.
     pub http_version: String,
     /// Header map plus typed helpers.
     pub headers: HTTPHeader,
-    /// Optional message body.
-    pub body: Option<Vec<u8>>,
+    /// Message body; [`Body::Empty`] when there is none.
+    pub body: Body,
+}
+
+/// The result of trying to parse an [`HTTPMessage`] off the wire.
+#[derive(Debug)]
+pub(crate) enum ParseError {
+    /// The buffer does not yet contain a complete message; read more bytes and
+    /// retry. Carries no position so it composes with streaming read loops.
+    Incomplete,
+    /// The input cannot be a valid HTTP message and should be rejected.
+    Malformed,
 }
 
 impl HTTPMessage {
     /// Creates a new message with the given version and headers.
     ///
-    /// The body is initially empty (`None`).
+    /// The body is initially empty ([`Body::Empty`]) and the start-line blank;
+    /// callers serializing the message should set [`start_line`](Self::start_line).
     pub(crate) fn new(http_version: String, headers: HTTPHeader) -> Self {
         Self {
+            start_line: String::new(),
             http_version,
             headers,
-            body: None,
+            body: Body::Empty,
+        }
+    }
+
+    /// Rewrites this message to an older HTTP version (typically `"HTTP/1.0"`),
+    /// fixing up the semantics that differ from HTTP/1.1 rather than only
+    /// overwriting [`http_version`](Self::http_version).
+    ///
+    /// HTTP/1.0 has no chunked transfer-coding, so a `Transfer-Encoding:
+    /// chunked` body is collapsed to its buffered form and described with an
+    /// explicit `Content-Length` instead. Persistent connections are opt-in in
+    /// 1.0, so `Connection` defaults to `close` unless the message already
+    /// carries `keep-alive`. Finally, 1.1-only hop-by-hop headers are dropped.
+    pub(crate) fn downgrade_to(&mut self, version: &str) {
+        self.http_version = version.to_string();
+
+        if self
+            .headers
+            .get("transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+        {
+            self.headers.remove("transfer-encoding");
+            // HTTP/1.0 cannot stream an unknown-length body, so the chunked
+            // body must be fully buffered before an accurate `Content-Length`
+            // can be advertised. A reader that outgrows `MAX_BODY_SIZE` (or
+            // fails) collapses to an empty body rather than emitting a length
+            // that disagrees with the bytes on the wire.
+            let buffered = std::mem::take(&mut self.body)
+                .collect(MAX_BODY_SIZE)
+                .unwrap_or_default();
+            let len = buffered.len();
+            self.body = Body::from(buffered);
+            self.headers.set("Content-Length", &len.to_string());
+        }
+
+        let keep_alive = self
+            .headers
+            .get("connection")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("keep-alive"));
+        self.headers
+            .set("Connection", if keep_alive { "keep-alive" } else { "close" });
+
+        for header in ["TE", "Trailer", "Upgrade", "Expect"] {
+            self.headers.remove(header);
         }
     }
+
+    /// Rewrites this message to a newer HTTP version (typically `"HTTP/1.1"`),
+    /// fixing up the semantics that 1.1 requires.
+    ///
+    /// HTTP/1.1 mandates a `Host` header, so `authority` (e.g. `example.com`)
+    /// is injected when the caller supplies one and no `Host` is present. The
+    /// `Connection` header is normalized to 1.1 defaults: an explicit `close`
+    /// is kept, anything else is dropped so the connection is persistent.
+    pub(crate) fn upgrade_to(&mut self, version: &str, authority: Option<&str>) {
+        self.http_version = version.to_string();
+
+        if let Some(authority) = authority {
+            if !self.headers.contains("host") {
+                self.headers.set("Host", authority);
+            }
+        }
+
+        match self
+            .headers
+            .get("connection")
+            .map(str::to_ascii_lowercase)
+        {
+            Some(value) if value.contains("close") => self.headers.set("Connection", "close"),
+            _ => {
+                self.headers.remove("connection");
+            }
+        }
+    }
+
+    /// Determines how this message's body should be framed on the wire.
+    ///
+    /// `Transfer-Encoding: chunked` takes precedence over `Content-Length`
+    /// per RFC 7230; otherwise a known body length is sent as [`BodySize::Sized`]
+    /// and an absent or empty body as [`BodySize::Empty`].
+    pub(crate) fn body_size(&self) -> BodySize {
+        if self
+            .headers
+            .get("transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+        {
+            return BodySize::Chunked;
+        }
+        match self.body.len() {
+            Some(0) => BodySize::Empty,
+            Some(len) => BodySize::Sized(len),
+            None => BodySize::Chunked,
+        }
+    }
+
+    /// Parses a single [`HTTPMessage`] from the front of `input`.
+    ///
+    /// On success returns the message together with the number of bytes
+    /// consumed, so a streaming read loop can advance its buffer and keep any
+    /// trailing bytes (e.g. a pipelined follow-up request). The body length is
+    /// taken from `Content-Length`, or decoded from `Transfer-Encoding:
+    /// chunked`; anything else is treated as a bodyless message.
+    ///
+    /// Returns [`ParseError::Incomplete`] when more bytes are needed and
+    /// [`ParseError::Malformed`] when the input cannot be valid HTTP or its
+    /// body exceeds `MAX_BODY_SIZE`.
+    ///
+    /// Raw header capture is disabled; see [`parse_raw`](Self::parse_raw) when
+    /// the exact on-the-wire header bytes must survive a round-trip.
+    pub(crate) fn parse(input: &[u8]) -> Result<(Self, usize), ParseError> {
+        Self::parse_inner(input, false)
+    }
+
+    /// Like [`parse`](Self::parse) but records the raw, on-the-wire
+    /// `(name, value)` header bytes so [`to_bytes`](Self::to_bytes) re-emits
+    /// them byte-for-byte — preserving casing, duplicate ordering and unusual
+    /// whitespace for fingerprinting, signature verification and transparent
+    /// proxying.
+    pub(crate) fn parse_raw(input: &[u8]) -> Result<(Self, usize), ParseError> {
+        Self::parse_inner(input, true)
+    }
+
+    fn parse_inner(input: &[u8], capture_raw: bool) -> Result<(Self, usize), ParseError> {
+        let head_end = input
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or(ParseError::Incomplete)?;
+        let head = &input[..head_end];
+        let body_start = head_end + 4;
+
+        let mut lines = split_crlf(head);
+        let start_line = lines.next().ok_or(ParseError::Malformed)?;
+        let start_line = String::from_utf8_lossy(start_line).into_owned();
+        let http_version = start_line
+            .split_whitespace()
+            .find(|token| token.starts_with("HTTP/"))
+            .ok_or(ParseError::Malformed)?
+            .to_string();
+
+        let mut headers = if capture_raw {
+            HTTPHeader::with_raw()
+        } else {
+            HTTPHeader::new()
+        };
+        let mut last: Option<String> = None;
+        for line in lines {
+            if matches!(line.first(), Some(b' ' | b'\t')) {
+                // Obsolete line folding: append the continuation to the
+                // previous field value.
+                let name = last.as_deref().ok_or(ParseError::Malformed)?;
+                let cont = String::from_utf8_lossy(line);
+                let merged = match headers.get(name) {
+                    Some(prev) => format!("{prev} {}", cont.trim()),
+                    None => cont.trim().to_string(),
+                };
+                headers.set(name, &merged);
+                continue;
+            }
+            let colon = line.iter().position(|&b| b == b':').ok_or(ParseError::Malformed)?;
+            let name = String::from_utf8_lossy(&line[..colon]).trim().to_string();
+            if name.is_empty() {
+                return Err(ParseError::Malformed);
+            }
+            // `push_raw` records the verbatim bytes (when capture is on) and
+            // combines repeated fields into the normalized map, so a duplicate
+            // keeps every value instead of collapsing to the last line seen.
+            headers.push_raw(&line[..colon], &line[colon + 1..]);
+            last = Some(name);
+        }
+
+        let rest = &input[body_start..];
+        let (body, consumed) = if headers
+            .get("transfer-encoding")
+            .is_some_and(|v| v.to_ascii_lowercase().contains("chunked"))
+        {
+            let decoded = match chunked::decode(rest, MAX_BODY_SIZE) {
+                Ok(decoded) => decoded,
+                Err(chunked::ChunkedError::Incomplete) => return Err(ParseError::Incomplete),
+                Err(_) => return Err(ParseError::Malformed),
+            };
+            for (name, value) in decoded.trailers.iter() {
+                headers.set(name, value);
+            }
+            (Body::from(decoded.body), body_start + decoded.consumed)
+        } else if let Some(value) = headers.get("content-length") {
+            let len: usize = value.trim().parse().map_err(|_| ParseError::Malformed)?;
+            if len > MAX_BODY_SIZE {
+                return Err(ParseError::Malformed);
+            }
+            if rest.len() < len {
+                return Err(ParseError::Incomplete);
+            }
+            (Body::from(rest[..len].to_vec()), body_start + len)
+        } else {
+            (Body::Empty, body_start)
+        };
+
+        Ok((
+            Self {
+                start_line,
+                http_version,
+                headers,
+                body,
+            },
+            consumed,
+        ))
+    }
+
+    /// Serializes the message back to its on-the-wire bytes with correct CRLF
+    /// framing.
+    ///
+    /// Only in-memory bodies are emitted; a streamed [`Body::Reader`] body is
+    /// skipped and must be written separately by the caller. When the headers
+    /// request chunked transfer-coding, the in-memory body is re-framed as a
+    /// single chunk plus the terminating zero-size chunk. The start-line is
+    /// taken from [`start_line`](Self::start_line), falling back to just the
+    /// version when it was built in memory.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let start_line = if self.start_line.is_empty() {
+            &self.http_version
+        } else {
+            &self.start_line
+        };
+        out.extend_from_slice(start_line.as_bytes());
+        out.extend_from_slice(b"\r\n");
+        if self.headers.captures_raw() {
+            // Re-emit the captured on-the-wire bytes verbatim, preserving
+            // casing, order, duplicates and the original post-colon whitespace.
+            for (name, value) in self.headers.raw_pairs() {
+                out.extend_from_slice(name);
+                out.push(b':');
+                out.extend_from_slice(value);
+                out.extend_from_slice(b"\r\n");
+            }
+        } else {
+            for (name, value) in self.headers.iter() {
+                // A `\n`-joined field (only `Set-Cookie`) was several headers
+                // on the wire; emit each on its own line to round-trip faithfully.
+                for value in value.split('\n') {
+                    out.extend_from_slice(name.as_bytes());
+                    out.extend_from_slice(b": ");
+                    out.extend_from_slice(value.as_bytes());
+                    out.extend_from_slice(b"\r\n");
+                }
+            }
+        }
+        out.extend_from_slice(b"\r\n");
+        let in_memory: Option<&[u8]> = match &self.body {
+            Body::Empty => Some(&[]),
+            Body::Bytes(bytes) => Some(bytes),
+            Body::Reader { .. } => None,
+        };
+        if let Some(bytes) = in_memory {
+            if self.headers.is_chunked() {
+                // The body is held decoded; re-frame it so it matches the
+                // `Transfer-Encoding: chunked` header written above.
+                out.extend_from_slice(&chunked::encode(bytes, bytes.len().max(1)));
+            } else {
+                out.extend_from_slice(bytes);
+            }
+        }
+        out
+    }
+}
+
+/// Upper bound on a parsed body to guard against memory exhaustion.
+const MAX_BODY_SIZE: usize = 8 * 1024 * 1024;
+
+/// Splits a byte slice into CRLF-terminated lines, skipping the final empty
+/// segment that a trailing CRLF would otherwise produce.
+fn split_crlf(input: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = input;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        match rest.windows(2).position(|w| w == b"\r\n") {
+            Some(idx) => {
+                let line = &rest[..idx];
+                rest = &rest[idx + 2..];
+                Some(line)
+            }
+            None => {
+                let line = rest;
+                rest = &[];
+                Some(line)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrade_buffers_chunked_body_and_sets_content_length() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Transfer-Encoding", "chunked");
+        let mut msg = HTTPMessage::new("HTTP/1.1".to_string(), headers);
+        msg.body = Body::from(b"payload".to_vec());
+
+        msg.downgrade_to("HTTP/1.0");
+
+        assert_eq!(msg.http_version, "HTTP/1.0");
+        assert!(msg.headers.get("transfer-encoding").is_none());
+        assert_eq!(msg.headers.get("content-length"), Some("7"));
+        assert!(matches!(msg.body, Body::Bytes(ref b) if b == b"payload"));
+    }
+
+    #[test]
+    fn downgrade_defaults_connection_to_close() {
+        let mut msg = HTTPMessage::new("HTTP/1.1".to_string(), HTTPHeader::new());
+        msg.downgrade_to("HTTP/1.0");
+        assert_eq!(msg.headers.get("connection"), Some("close"));
+    }
+
+    #[test]
+    fn downgrade_preserves_keep_alive() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Connection", "keep-alive");
+        let mut msg = HTTPMessage::new("HTTP/1.1".to_string(), headers);
+        msg.downgrade_to("HTTP/1.0");
+        assert_eq!(msg.headers.get("connection"), Some("keep-alive"));
+    }
+
+    #[test]
+    fn downgrade_strips_http11_only_headers() {
+        let mut headers = HTTPHeader::new();
+        headers.set("TE", "trailers");
+        headers.set("Trailer", "X-Checksum");
+        headers.set("Upgrade", "h2c");
+        headers.set("Expect", "100-continue");
+        let mut msg = HTTPMessage::new("HTTP/1.1".to_string(), headers);
+
+        msg.downgrade_to("HTTP/1.0");
+
+        for header in ["te", "trailer", "upgrade", "expect"] {
+            assert!(msg.headers.get(header).is_none(), "{header} should be stripped");
+        }
+    }
+
+    #[test]
+    fn upgrade_injects_host_only_when_absent() {
+        let mut msg = HTTPMessage::new("HTTP/1.0".to_string(), HTTPHeader::new());
+        msg.upgrade_to("HTTP/1.1", Some("example.com"));
+        assert_eq!(msg.http_version, "HTTP/1.1");
+        assert_eq!(msg.headers.get("host"), Some("example.com"));
+
+        let mut headers = HTTPHeader::new();
+        headers.set("Host", "original.example");
+        let mut msg = HTTPMessage::new("HTTP/1.0".to_string(), headers);
+        msg.upgrade_to("HTTP/1.1", Some("example.com"));
+        assert_eq!(msg.headers.get("host"), Some("original.example"));
+    }
+
+    #[test]
+    fn upgrade_keeps_explicit_close_and_drops_other_connection_values() {
+        let mut headers = HTTPHeader::new();
+        headers.set("Connection", "close");
+        let mut msg = HTTPMessage::new("HTTP/1.0".to_string(), headers);
+        msg.upgrade_to("HTTP/1.1", None);
+        assert_eq!(msg.headers.get("connection"), Some("close"));
+
+        let mut headers = HTTPHeader::new();
+        headers.set("Connection", "keep-alive");
+        let mut msg = HTTPMessage::new("HTTP/1.0".to_string(), headers);
+        msg.upgrade_to("HTTP/1.1", None);
+        assert!(msg.headers.get("connection").is_none());
+    }
+
+    #[test]
+    fn body_size_resolves_framing() {
+        let mut chunked = HTTPHeader::new();
+        chunked.set("Transfer-Encoding", "chunked");
+        let msg = HTTPMessage::new("HTTP/1.1".to_string(), chunked);
+        assert_eq!(msg.body_size(), BodySize::Chunked);
+
+        let mut msg = HTTPMessage::new("HTTP/1.1".to_string(), HTTPHeader::new());
+        msg.body = Body::from(b"hi".to_vec());
+        assert_eq!(msg.body_size(), BodySize::Sized(2));
+
+        let msg = HTTPMessage::new("HTTP/1.1".to_string(), HTTPHeader::new());
+        assert_eq!(msg.body_size(), BodySize::Empty);
+    }
+
+    #[test]
+    fn parses_request_with_content_length_body() {
+        let (msg, consumed) =
+            HTTPMessage::parse(b"GET / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+        assert_eq!(msg.http_version, "HTTP/1.1");
+        assert_eq!(msg.headers.get("content-length"), Some("5"));
+        assert!(matches!(msg.body, Body::Bytes(ref b) if b == b"hello"));
+        assert_eq!(consumed, 42);
+    }
+
+    #[test]
+    fn incomplete_until_headers_terminated() {
+        assert!(matches!(
+            HTTPMessage::parse(b"GET / HTTP/1.1\r\nHost: x"),
+            Err(ParseError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn incomplete_until_body_arrives() {
+        assert!(matches!(
+            HTTPMessage::parse(b"GET / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhel"),
+            Err(ParseError::Incomplete)
+        ));
+    }
+
+    #[test]
+    fn malformed_header_line_without_colon() {
+        assert!(matches!(
+            HTTPMessage::parse(b"GET / HTTP/1.1\r\nbroken\r\n\r\n"),
+            Err(ParseError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn malformed_start_line_without_version() {
+        assert!(matches!(
+            HTTPMessage::parse(b"GET /\r\n\r\n"),
+            Err(ParseError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn combinable_duplicates_are_comma_joined() {
+        let (msg, _) = HTTPMessage::parse(
+            b"GET / HTTP/1.1\r\nAccept-Encoding: gzip\r\nAccept-Encoding: br\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(msg.headers.get("accept-encoding"), Some("gzip, br"));
+    }
+
+    #[test]
+    fn duplicate_set_cookie_is_preserved_and_re_emitted() {
+        let (msg, _) = HTTPMessage::parse(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nSet-Cookie: b=2\r\n\r\n",
+        )
+        .unwrap();
+        let wire = msg.to_bytes();
+        let text = String::from_utf8_lossy(&wire);
+        assert!(text.contains("set-cookie: a=1\r\n"));
+        assert!(text.contains("set-cookie: b=2\r\n"));
+    }
+
+    #[test]
+    fn raw_capture_round_trips_byte_faithfully() {
+        let input = b"GET / HTTP/1.1\r\nX-My-Header: One\r\nHost:   example.com\r\n\r\n";
+        let (msg, _) = HTTPMessage::parse_raw(input).unwrap();
+        assert!(msg.headers.captures_raw());
+        // Start-line plus the exact header bytes (casing and spacing) are kept.
+        let wire = msg.to_bytes();
+        assert_eq!(&wire, input);
+    }
+
+    #[test]
+    fn parse_rejects_oversize_content_length() {
+        let mut input = b"POST / HTTP/1.1\r\nContent-Length: 9999999999\r\n\r\n".to_vec();
+        input.extend_from_slice(&[0u8; 16]);
+        assert!(matches!(
+            HTTPMessage::parse(&input),
+            Err(ParseError::Malformed)
+        ));
+    }
 }